@@ -10,6 +10,11 @@
 //!   - メモリは10個まで保持可能
 //!   - メモリには名前付きで(例("名前",値)のタプルでの表現)が可能
 //!   - 括弧でくくった計算式を優先して処理
+//!   - `identifier = 計算式` で計算結果を任意の名前の変数に代入できる
+//!   - `sin`/`cos`/`sqrt`/`abs`/`factorial` 関数を `関数名(計算式)` の形で呼び出せる
+//!   - `mem?` で保持しているメモリを一覧表示、`memclear <名前>` で指定したメモリを削除、
+//!     `memclear` のみで全メモリを削除できる
+//!   - 行頭に `vm:` を付けると、木を辿る代わりにスタックマシンにコンパイルして評価する
 //! - 計算結果は整数型ではなく小数型(f64)で管理
 //!
 //! 例
@@ -33,22 +38,32 @@ struct Memory {
 }
 
 impl Memory {
+    /// 保持可能なメモリの最大数
+    const CAPACITY: usize = 10;
+
     /// Memory構造体の初期化
     fn new() -> Self {
         Self {
             slots: HashMap::new(),
         }
     }
+    /// 新規に`slot_name`を追加する余地があるかどうか
+    fn has_capacity_for(&self, slot_name: &str) -> bool {
+        self.slots.contains_key(slot_name) || self.slots.len() < Self::CAPACITY
+    }
     /// メモリの追加、更新処理
-    fn add(&mut self, slot_name: String, prev_result: f64) -> f64 {
+    fn add(&mut self, slot_name: String, prev_result: f64) -> Result<f64, CalcError> {
+        if !self.has_capacity_for(&slot_name) {
+            return Err(CalcError::MemoryFull);
+        }
         match self.slots.entry(slot_name) {
             Entry::Occupied(mut entry) => {
                 *entry.get_mut() += prev_result;
-                *entry.get()
+                Ok(*entry.get())
             }
             Entry::Vacant(entry) => {
                 entry.insert(prev_result);
-                prev_result
+                Ok(prev_result)
             }
         }
     }
@@ -56,6 +71,139 @@ impl Memory {
     fn get(&self, slot_name: &str) -> f64 {
         self.slots.get(slot_name).copied().unwrap_or(0.0)
     }
+    /// 指定した名前のメモリが存在するかどうか
+    fn contains(&self, slot_name: &str) -> bool {
+        self.slots.contains_key(slot_name)
+    }
+    /// 変数への代入処理(累積せず値を上書きする)
+    fn set(&mut self, slot_name: String, value: f64) -> Result<(), CalcError> {
+        if !self.has_capacity_for(&slot_name) {
+            return Err(CalcError::MemoryFull);
+        }
+        self.slots.insert(slot_name, value);
+        Ok(())
+    }
+    /// 保持している(名前, 値)の一覧を名前順で取得する処理
+    fn list(&self) -> Vec<(String, f64)> {
+        let mut slots: Vec<(String, f64)> = self
+            .slots
+            .iter()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect();
+        slots.sort_by(|(a, _), (b, _)| a.cmp(b));
+        slots
+    }
+    /// 指定した名前のメモリを削除する処理
+    fn remove(&mut self, slot_name: &str) {
+        self.slots.remove(slot_name);
+    }
+    /// メモリを全て空にする処理
+    fn clear(&mut self) {
+        self.slots.clear();
+    }
+}
+
+/// 計算処理で発生しうるエラー
+#[derive(Debug, PartialEq)]
+enum CalcError {
+    // 想定外のトークンが出現した
+    UnexpectedToken,
+    // トークン列が途中で尽きた
+    UnexpectedEnd,
+    // 括弧の対応が取れていない
+    UnmatchedParen,
+    // 数値としてパースできなかった文字列
+    InvalidNumber(String),
+    // ゼロ除算
+    DivisionByZero,
+    // メモリの保持上限(10個)を超えた
+    MemoryFull,
+    // factorialの引数が大きすぎる
+    FactorialTooLarge(f64),
+}
+impl std::fmt::Display for CalcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedToken => write!(f, "unexpected token"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of input"),
+            Self::UnmatchedParen => write!(f, "unmatched parenthesis"),
+            Self::InvalidNumber(text) => write!(f, "invalid number: {text}"),
+            Self::DivisionByZero => write!(f, "division by zero"),
+            Self::MemoryFull => write!(f, "memory is full (max {} slots)", Memory::CAPACITY),
+            Self::FactorialTooLarge(value) => write!(
+                f,
+                "factorial argument too large: {value} (max {})",
+                Func::FACTORIAL_MAX
+            ),
+        }
+    }
+}
+
+/// 二項演算子
+#[derive(Debug, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// 組み込み関数
+#[derive(Debug, Clone, PartialEq)]
+enum Func {
+    Sin,
+    Cos,
+    Sqrt,
+    Abs,
+    Factorial,
+}
+impl Func {
+    /// factorialに渡せる引数の上限(170!がf64で有限に表せる最大の階乗。
+    /// 上限がないと巨大な引数でREPLが長時間ブロックする)
+    const FACTORIAL_MAX: i64 = 170;
+
+    /// 関数名からFuncへの変換処理
+    fn from_name(name: &str) -> Result<Self, CalcError> {
+        match name {
+            "sin" => Ok(Self::Sin),
+            "cos" => Ok(Self::Cos),
+            "sqrt" => Ok(Self::Sqrt),
+            "abs" => Ok(Self::Abs),
+            "factorial" => Ok(Self::Factorial),
+            _ => Err(CalcError::UnexpectedToken),
+        }
+    }
+}
+/// 組み込み関数の適用処理
+fn apply_func(func: &Func, arg: f64) -> Result<f64, CalcError> {
+    match func {
+        Func::Sin => Ok(arg.sin()),
+        Func::Cos => Ok(arg.cos()),
+        Func::Sqrt => Ok(arg.sqrt()),
+        Func::Abs => Ok(arg.abs()),
+        Func::Factorial => {
+            let n = arg as i64;
+            if n > Func::FACTORIAL_MAX {
+                return Err(CalcError::FactorialTooLarge(arg));
+            }
+            Ok((1..=n).fold(1.0, |acc, value| acc * value as f64))
+        }
+    }
+}
+
+/// 構文木(AST)
+///
+/// パース結果を表現し、評価・変換・再利用を可能にする
+#[derive(Debug, PartialEq)]
+enum Expr {
+    // 数値リテラル
+    Number(f64),
+    // メモリ参照(変数参照)
+    MemoryRef(String),
+    // 二項演算
+    Binary(Op, Box<Expr>, Box<Expr>),
+    // 関数呼び出し
+    Call(Func, Box<Expr>),
 }
 
 /// トークン列挙体
@@ -69,6 +217,10 @@ enum Token {
     MemoryPlus(String),
     // 減算メモリ
     MemoryMinus(String),
+    // 識別子(関数名または変数名)
+    Ident(String),
+    // 代入演算子"="
+    Equals,
     // 加算演算子
     Plus,
     // 減算演算子
@@ -83,34 +235,133 @@ enum Token {
     RParen,
 }
 impl Token {
-    /// トークンのパース処理
-    fn parse(value: &str) -> Self {
-        match value {
-            "+" => Self::Plus,
-            "-" => Self::Minus,
-            "*" => Self::Asterisk,
-            "/" => Self::Slash,
-            "(" => Self::LParen,
-            ")" => Self::RParen,
-            // 上記にあてはまらないかつ、memで始まる場合
-            _ if value.starts_with("mem") => {
-                let mut memory_name = value[3..].to_string();
-                if value.ends_with('+') {
-                    memory_name.pop();
-                    Self::MemoryPlus(memory_name)
-                } else if value.ends_with('-') {
-                    memory_name.pop();
-                    Self::MemoryMinus(memory_name)
-                } else {
-                    Self::MemoryRef(memory_name)
+    /// 入力値の分割とパース処理結果の取得(字句解析器に委譲)
+    fn split(text: &str) -> Result<Vec<Self>, CalcError> {
+        let mut lexer = Lexer::new(text);
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token()? {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+}
+
+/// 識別子(mem<memory_name>)に使える文字かどうか
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 文字単位でトークンを読み取る字句解析器
+///
+/// `Token::split` が空白区切りに依存していたため、`1+2*(3-4)` のような
+/// 空白なし入力を扱えなかった。1文字ずつカーソルを進めて読み取ることで
+/// 空白の有無に依存しないトークナイズを行う
+struct Lexer {
+    chars: Vec<char>,
+    position: usize,
+}
+impl Lexer {
+    fn new(text: &str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            position: 0,
+        }
+    }
+    /// 次の1トークンを読み取る。入力が尽きたら`None`を返す
+    fn next_token(&mut self) -> Result<Option<Token>, CalcError> {
+        self.skip_whitespace();
+        let Some(c) = self.chars.get(self.position).copied() else {
+            return Ok(None);
+        };
+        let token = match c {
+            '+' => {
+                self.position += 1;
+                Token::Plus
+            }
+            '-' => {
+                self.position += 1;
+                Token::Minus
+            }
+            '*' => {
+                self.position += 1;
+                Token::Asterisk
+            }
+            '/' => {
+                self.position += 1;
+                Token::Slash
+            }
+            '(' => {
+                self.position += 1;
+                Token::LParen
+            }
+            ')' => {
+                self.position += 1;
+                Token::RParen
+            }
+            '=' => {
+                self.position += 1;
+                Token::Equals
+            }
+            _ if c.is_alphabetic() => self.read_identifier(),
+            _ if c.is_ascii_digit() || c == '.' => self.read_number()?,
+            _ => return Err(CalcError::UnexpectedToken),
+        };
+        Ok(Some(token))
+    }
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.get(self.position), Some(c) if c.is_whitespace()) {
+            self.position += 1;
+        }
+    }
+    /// 数字と`.`が連続する範囲をNumberとして読み取る
+    fn read_number(&mut self) -> Result<Token, CalcError> {
+        let start = self.position;
+        while matches!(self.chars.get(self.position), Some(c) if c.is_ascii_digit() || *c == '.') {
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse()
+            .map(Token::Number)
+            .map_err(|_| CalcError::InvalidNumber(text))
+    }
+    /// 識別子を読み取る。`mem`で始まる場合はメモリ参照系のトークン
+    /// (と任意の末尾`+`/`-`)に、それ以外は`Ident`になる
+    ///
+    /// `member`や`memo`のような、`mem`で始まるが代入対象として使われる識別子は
+    /// メモリ参照と衝突してしまう。直後(空白を挟んでもよい)が`=`であれば代入文の
+    /// 左辺とみなし、`mem`を剥がさずそのまま`Ident`として扱う
+    fn read_identifier(&mut self) -> Token {
+        let start = self.position;
+        while matches!(self.chars.get(self.position), Some(c) if is_identifier_char(*c)) {
+            self.position += 1;
+        }
+        let word: String = self.chars[start..self.position].iter().collect();
+        match word.strip_prefix("mem") {
+            Some(memory_name) => {
+                let memory_name = memory_name.to_string();
+                match self.chars.get(self.position) {
+                    Some('+') => {
+                        self.position += 1;
+                        Token::MemoryPlus(memory_name)
+                    }
+                    Some('-') => {
+                        self.position += 1;
+                        Token::MemoryMinus(memory_name)
+                    }
+                    _ if self.next_non_whitespace_is(b'=') => Token::Ident(word),
+                    _ => Token::MemoryRef(memory_name),
                 }
             }
-            _ => Self::Number(value.parse().unwrap()),
+            None => Token::Ident(word),
         }
     }
-    /// 入力値の分割とパース処理結果の取得
-    fn split(text: &str) -> Vec<Self> {
-        text.split(char::is_whitespace).map(Self::parse).collect()
+    /// 現在位置から空白を読み飛ばした先が、指定した文字かどうかを先読みする
+    fn next_non_whitespace_is(&self, expected: u8) -> bool {
+        let mut position = self.position;
+        while matches!(self.chars.get(position), Some(c) if c.is_whitespace()) {
+            position += 1;
+        }
+        matches!(self.chars.get(position), Some(c) if *c == expected as char)
     }
 }
 /// メイン処理
@@ -125,118 +376,413 @@ fn main() {
             print!("Bye!");
             break;
         }
-        // 入力を空白区切りで分割
-        let tokens: Vec<Token> = Token::split(&line);
+        if let Err(err) = process_line(&line, &mut memories, &mut prev_result) {
+            println!("Error: {err}");
+        }
+    }
+}
+/// 1行分の入力を処理する。計算式として不正な場合はエラーを返す
+fn process_line(line: &str, memories: &mut Memory, prev_result: &mut f64) -> Result<(), CalcError> {
+    // メモリ管理用の特殊コマンドはトークン化の前に処理する
+    if line == "mem?" {
+        for (name, value) in memories.list() {
+            println!("{name} = {value}");
+        }
+        return Ok(());
+    }
+    if line == "memclear" {
+        memories.clear();
+        return Ok(());
+    }
+    if let Some(name) = line.strip_prefix("memclear ") {
+        let name = name.trim();
+        if name.is_empty() {
+            memories.clear();
+        } else if memories.contains(name) {
+            memories.remove(name);
+        } else if let Some(stripped) = name.strip_prefix("mem").filter(|s| memories.contains(s)) {
+            // mem<name>+/-で作られたメモリは、"mem"を剥がした名前で保持されている
+            memories.remove(stripped);
+        } else {
+            println!("no such memory: {name}");
+        }
+        return Ok(());
+    }
+    // `vm:`を先頭につけると、スタックマシンにコンパイルしてから評価する
+    if let Some(formula) = line.strip_prefix("vm:") {
+        let tokens = Token::split(formula)?;
+        let result = eval_expression_via_vm(&tokens, memories)?;
+        print_formula_result(line.to_string(), result);
+        *prev_result = result;
+        return Ok(());
+    }
 
-        // トークンによって処理を分岐
-        match &tokens[0] {
-            Token::MemoryPlus(memory_name) => {
-                let memory_name = memory_name.to_string();
-                let result = memories.add(memory_name, prev_result);
-                print_formula_result(line, result);
-            }
-            Token::MemoryMinus(memory_name) => {
-                let memory_name = memory_name.to_string();
-                let result = memories.add(memory_name, -prev_result);
-                print_formula_result(line, result);
-            }
-            _ => {
-                let result = eval_expression(&tokens, &memories);
-                print_formula_result(line, result);
-                prev_result = result;
+    // 入力を字句解析器でトークンに分割(空白の有無は問わない)
+    let tokens = Token::split(line)?;
+    let first_token = tokens.first().ok_or(CalcError::UnexpectedEnd)?;
+
+    // トークンによって処理を分岐
+    match first_token {
+        Token::MemoryPlus(memory_name) => {
+            let memory_name = memory_name.to_string();
+            let result = memories.add(memory_name, *prev_result)?;
+            print_formula_result(line.to_string(), result);
+        }
+        Token::MemoryMinus(memory_name) => {
+            let memory_name = memory_name.to_string();
+            let result = memories.add(memory_name, -*prev_result)?;
+            print_formula_result(line.to_string(), result);
+        }
+        Token::Ident(name) if tokens.get(1) == Some(&Token::Equals) => {
+            let name = name.to_string();
+            let result = eval_expression(&tokens[2..], memories)?;
+            memories.set(name, result)?;
+            print_formula_result(line.to_string(), result);
+            *prev_result = result;
+        }
+        _ => {
+            let result = eval_expression(&tokens, memories)?;
+            print_formula_result(line.to_string(), result);
+            *prev_result = result;
+        }
+    }
+    Ok(())
+}
+
+/// 構文木の評価処理
+fn eval(expr: &Expr, memory: &Memory) -> Result<f64, CalcError> {
+    match expr {
+        Expr::Number(value) => Ok(*value),
+        Expr::MemoryRef(memory_name) => Ok(memory.get(memory_name)),
+        Expr::Binary(op, lhs, rhs) => {
+            let lhs = eval(lhs, memory)?;
+            let rhs = eval(rhs, memory)?;
+            match op {
+                Op::Add => Ok(lhs + rhs),
+                Op::Sub => Ok(lhs - rhs),
+                Op::Mul => Ok(lhs * rhs),
+                Op::Div if rhs == 0.0 => Err(CalcError::DivisionByZero),
+                Op::Div => Ok(lhs / rhs),
             }
         }
+        Expr::Call(func, arg) => apply_func(func, eval(arg, memory)?),
+    }
+}
+/// 式の計算処理の解釈(木を辿って評価)
+fn eval_expression(tokens: &[Token], memory: &Memory) -> Result<f64, CalcError> {
+    let (expr, index) = eval_additive_expression(tokens, 0)?;
+    if index != tokens.len() {
+        return Err(CalcError::UnexpectedToken);
     }
+    eval(&expr, memory)
+}
+/// 式の計算処理の解釈(スタックマシンへコンパイルして評価)。
+/// REPLでは行頭に`vm:`を付けると呼び出される
+fn eval_expression_via_vm(tokens: &[Token], memory: &Memory) -> Result<f64, CalcError> {
+    let (expr, index) = eval_additive_expression(tokens, 0)?;
+    if index != tokens.len() {
+        return Err(CalcError::UnexpectedToken);
+    }
+    let instrs = compile(&expr);
+    run(&instrs, memory)
 }
 
-/// トークンの解釈処理
-fn eval_token(token: &Token, memory: &Memory) -> f64 {
-    match token {
-        Token::Number(value) => *value,
-        Token::MemoryRef(memory_name) => memory.get(memory_name),
-        _ => unreachable!(),
+/// スタックマシン向けの命令
+#[derive(Debug, PartialEq)]
+enum Instr {
+    Push(f64),
+    Load(String),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Call(Func),
+}
+/// 構文木をスタックマシンの命令列へコンパイルする処理(後順走査)
+fn compile(expr: &Expr) -> Vec<Instr> {
+    let mut instrs = Vec::new();
+    compile_into(expr, &mut instrs);
+    instrs
+}
+fn compile_into(expr: &Expr, instrs: &mut Vec<Instr>) {
+    match expr {
+        Expr::Number(value) => instrs.push(Instr::Push(*value)),
+        Expr::MemoryRef(memory_name) => instrs.push(Instr::Load(memory_name.clone())),
+        Expr::Binary(op, lhs, rhs) => {
+            compile_into(lhs, instrs);
+            compile_into(rhs, instrs);
+            instrs.push(match op {
+                Op::Add => Instr::Add,
+                Op::Sub => Instr::Sub,
+                Op::Mul => Instr::Mul,
+                Op::Div => Instr::Div,
+            });
+        }
+        Expr::Call(func, arg) => {
+            compile_into(arg, instrs);
+            instrs.push(Instr::Call(func.clone()));
+        }
     }
 }
-/// 式の計算処理の解釈
-fn eval_expression(tokens: &[Token], memory: &Memory) -> f64 {
-    let (result, index) = eval_additive_expression(tokens, 0, memory);
-    assert_eq!(tokens.len(), index);
-    result
+/// スタックマシンの命令列を実行する処理
+fn run(instrs: &[Instr], memory: &Memory) -> Result<f64, CalcError> {
+    let mut stack: Vec<f64> = Vec::new();
+    for instr in instrs {
+        match instr {
+            Instr::Push(value) => stack.push(*value),
+            Instr::Load(memory_name) => stack.push(memory.get(memory_name)),
+            Instr::Add | Instr::Sub | Instr::Mul | Instr::Div => {
+                let rhs = stack.pop().expect("missing rhs operand on stack");
+                let lhs = stack.pop().expect("missing lhs operand on stack");
+                if matches!(instr, Instr::Div) && rhs == 0.0 {
+                    return Err(CalcError::DivisionByZero);
+                }
+                stack.push(match instr {
+                    Instr::Add => lhs + rhs,
+                    Instr::Sub => lhs - rhs,
+                    Instr::Mul => lhs * rhs,
+                    Instr::Div => lhs / rhs,
+                    _ => unreachable!(),
+                });
+            }
+            Instr::Call(func) => {
+                let arg = stack.pop().expect("missing operand on stack");
+                stack.push(apply_func(func, arg)?);
+            }
+        }
+    }
+    assert_eq!(1, stack.len());
+    Ok(stack[0])
 }
-/// 加減算処理
-fn eval_additive_expression(tokens: &[Token], index: usize, memory: &Memory) -> (f64, usize) {
+/// 加減算の構文木構築処理
+fn eval_additive_expression(tokens: &[Token], index: usize) -> Result<(Expr, usize), CalcError> {
     let mut index: usize = index;
-    let mut result: f64;
+    let mut result: Expr;
 
-    (result, index) = eval_multiplicative_expression(tokens, index, memory);
+    (result, index) = eval_multiplicative_expression(tokens, index)?;
     while index < tokens.len() {
         match &tokens[index] {
             Token::Plus => {
-                let (value, next) = eval_multiplicative_expression(tokens, index + 1, memory);
-                result += value;
+                let (rhs, next) = eval_multiplicative_expression(tokens, index + 1)?;
+                result = Expr::Binary(Op::Add, Box::new(result), Box::new(rhs));
                 index = next;
             }
             Token::Minus => {
-                let (value, next) = eval_multiplicative_expression(tokens, index + 1, memory);
-                result -= value;
+                let (rhs, next) = eval_multiplicative_expression(tokens, index + 1)?;
+                result = Expr::Binary(Op::Sub, Box::new(result), Box::new(rhs));
                 index = next;
             }
             _ => break,
         }
     }
-    (result, index)
+    Ok((result, index))
 }
-/// 乗除算処理
-fn eval_multiplicative_expression(tokens: &[Token], index: usize, memory: &Memory) -> (f64, usize) {
+/// 乗除算の構文木構築処理
+fn eval_multiplicative_expression(
+    tokens: &[Token],
+    index: usize,
+) -> Result<(Expr, usize), CalcError> {
     let mut index: usize = index;
-    let mut result: f64;
-    (result, index) = eval_primary_expression(tokens, index, memory);
+    let mut result: Expr;
+    (result, index) = eval_unary_expression(tokens, index)?;
 
     while index < tokens.len() {
         match &tokens[index] {
             Token::Asterisk => {
-                let (value, next) = eval_primary_expression(tokens, index, memory);
-                result *= value;
+                let (rhs, next) = eval_unary_expression(tokens, index + 1)?;
+                result = Expr::Binary(Op::Mul, Box::new(result), Box::new(rhs));
                 index = next;
             }
             Token::Slash => {
-                let (value, next) = eval_primary_expression(tokens, index, memory);
-                result /= value;
+                let (rhs, next) = eval_unary_expression(tokens, index + 1)?;
+                result = Expr::Binary(Op::Div, Box::new(result), Box::new(rhs));
                 index = next;
             }
             _ => break,
         }
     }
-    (result, index)
+    Ok((result, index))
 }
-/// 括弧の処理
-fn eval_primary_expression(tokens: &[Token], index: usize, memory: &Memory) -> (f64, usize) {
-    let first_token = &tokens[index];
-    dbg!(first_token);
+/// 単項マイナスの構文木構築処理
+///
+/// `-5`や`-(1 + 2)`のような先頭の`-`を処理する。`--5`のような符号の
+/// 連続も、自身を再帰呼び出しすることで対応する。二項の減算とは
+/// 出現位置(項の先頭であること)で区別する
+fn eval_unary_expression(tokens: &[Token], index: usize) -> Result<(Expr, usize), CalcError> {
+    match tokens.get(index).ok_or(CalcError::UnexpectedEnd)? {
+        Token::Minus => {
+            let (expr, next) = eval_unary_expression(tokens, index + 1)?;
+            Ok((
+                Expr::Binary(Op::Sub, Box::new(Expr::Number(0.0)), Box::new(expr)),
+                next,
+            ))
+        }
+        _ => eval_primary_expression(tokens, index),
+    }
+}
+/// 括弧・数値・メモリ参照の構文木構築処理
+fn eval_primary_expression(tokens: &[Token], index: usize) -> Result<(Expr, usize), CalcError> {
+    let first_token = tokens.get(index).ok_or(CalcError::UnexpectedEnd)?;
     match first_token {
         Token::LParen => {
             // 開き括弧始まりであるため、括弧の直後のトークンから計算
-            let (result, next) = eval_additive_expression(tokens, index + 1, memory);
+            let (expr, next) = eval_additive_expression(tokens, index + 1)?;
             // 処理後は閉じ括弧終わりになっていることを検証
-            assert_eq!(Token::RParen, tokens[next]);
+            if tokens.get(next) != Some(&Token::RParen) {
+                return Err(CalcError::UnmatchedParen);
+            }
             // 閉じ括弧分を進めたindexで返す
-            dbg!(result, next + 1);
-            (result, next + 1)
+            Ok((expr, next + 1))
         }
         Token::Number(value) => {
             // 数値のためその値と次の値を返却
-            dbg!(*value, index + 1);
-            (*value, index + 1)
+            Ok((Expr::Number(*value), index + 1))
         }
         Token::MemoryRef(memory_name) => {
             // メモリを参照しているためその値と次の値を返却
-            dbg!(memory.get(memory_name), index + 1);
-            (memory.get(memory_name), index + 1)
+            Ok((Expr::MemoryRef(memory_name.clone()), index + 1))
         }
-        _ => unreachable!(),
+        Token::Ident(name) if matches!(tokens.get(index + 1), Some(Token::LParen)) => {
+            // 識別子の直後が開き括弧のため関数呼び出しとして解釈
+            let func = Func::from_name(name)?;
+            let (arg, next) = eval_additive_expression(tokens, index + 2)?;
+            // 処理後は閉じ括弧終わりになっていることを検証
+            if tokens.get(next) != Some(&Token::RParen) {
+                return Err(CalcError::UnmatchedParen);
+            }
+            Ok((Expr::Call(func, Box::new(arg)), next + 1))
+        }
+        Token::Ident(name) => {
+            // 関数呼び出しでない識別子は変数参照として解釈
+            Ok((Expr::MemoryRef(name.clone()), index + 1))
+        }
+        _ => Err(CalcError::UnexpectedToken),
     }
 }
 /// 計算結果出力
 fn print_formula_result(formula: String, result: f64) {
     println!("{} equal {}", formula, result);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mem_prefixed_identifier_can_be_assigned_without_erroring() {
+        let mut memories = Memory::new();
+        let mut prev_result = 0.0;
+        assert!(process_line("member = 5", &mut memories, &mut prev_result).is_ok());
+        assert!(process_line("memx = 3 + 4", &mut memories, &mut prev_result).is_ok());
+        assert!(process_line("memory = 7", &mut memories, &mut prev_result).is_ok());
+    }
+
+    #[test]
+    fn legacy_mem_slot_addressing_still_works() {
+        let mut memories = Memory::new();
+        let mut prev_result = 5.0;
+        assert!(process_line("mem1+", &mut memories, &mut prev_result).is_ok());
+        assert_eq!(memories.get("1"), 5.0);
+    }
+
+    #[test]
+    fn unary_minus_negates_literals_and_subexpressions() {
+        let memories = Memory::new();
+        assert_eq!(
+            eval_expression(&Token::split("-5").unwrap(), &memories).unwrap(),
+            -5.0
+        );
+        assert_eq!(
+            eval_expression(&Token::split("--5").unwrap(), &memories).unwrap(),
+            5.0
+        );
+        assert_eq!(
+            eval_expression(&Token::split("-(1 + 2)").unwrap(), &memories).unwrap(),
+            -3.0
+        );
+        assert_eq!(
+            eval_expression(&Token::split("3 * -2").unwrap(), &memories).unwrap(),
+            -6.0
+        );
+    }
+
+    #[test]
+    fn division_by_zero_is_reported_as_calc_error() {
+        let memories = Memory::new();
+        let result = eval_expression(&Token::split("1 / 0").unwrap(), &memories);
+        assert_eq!(result, Err(CalcError::DivisionByZero));
+    }
+
+    #[test]
+    fn unmatched_paren_is_reported_as_calc_error() {
+        let memories = Memory::new();
+        let result = eval_expression(&Token::split("(1 + 2").unwrap(), &memories);
+        assert_eq!(result, Err(CalcError::UnmatchedParen));
+    }
+
+    #[test]
+    fn invalid_number_is_reported_as_calc_error() {
+        let result = Token::split("1.2.3");
+        assert_eq!(result, Err(CalcError::InvalidNumber("1.2.3".to_string())));
+    }
+
+    #[test]
+    fn memory_full_is_reported_once_capacity_is_exceeded() {
+        let mut memories = Memory::new();
+        for i in 0..Memory::CAPACITY {
+            assert!(memories.add(i.to_string(), 1.0).is_ok());
+        }
+        let result = memories.add("one_too_many".to_string(), 1.0);
+        assert_eq!(result, Err(CalcError::MemoryFull));
+    }
+
+    #[test]
+    fn factorial_too_large_is_reported_as_calc_error() {
+        let result = apply_func(&Func::Factorial, (Func::FACTORIAL_MAX + 1) as f64);
+        assert_eq!(
+            result,
+            Err(CalcError::FactorialTooLarge(
+                (Func::FACTORIAL_MAX + 1) as f64
+            ))
+        );
+    }
+
+    #[test]
+    fn memclear_accepts_both_the_stripped_and_mem_prefixed_slot_name() {
+        let mut memories = Memory::new();
+        let mut prev_result = 5.0;
+        assert!(process_line("mem1+", &mut memories, &mut prev_result).is_ok());
+        assert!(process_line("memclear mem1", &mut memories, &mut prev_result).is_ok());
+        assert_eq!(memories.get("1"), 0.0);
+        assert!(!memories.contains("1"));
+    }
+
+    #[test]
+    fn memclear_matches_an_assigned_variable_by_its_full_name() {
+        let mut memories = Memory::new();
+        let mut prev_result = 0.0;
+        assert!(process_line("member = 5", &mut memories, &mut prev_result).is_ok());
+        assert!(process_line("memclear member", &mut memories, &mut prev_result).is_ok());
+        assert!(!memories.contains("member"));
+    }
+
+    #[test]
+    fn memclear_total_assignment_is_not_swallowed_by_the_memclear_command() {
+        let mut memories = Memory::new();
+        let mut prev_result = 0.0;
+        assert!(process_line("memclearTotal = 5", &mut memories, &mut prev_result).is_ok());
+        assert_eq!(memories.get("memclearTotal"), 5.0);
+    }
+
+    #[test]
+    fn vm_prefix_evaluates_via_the_stack_machine_backend() {
+        let memories = Memory::new();
+        let tokens = Token::split("2 + 3 * 4").unwrap();
+        assert_eq!(eval_expression_via_vm(&tokens, &memories).unwrap(), 14.0);
+
+        let mut memories = Memory::new();
+        let mut prev_result = 0.0;
+        assert!(process_line("vm: 2 + 3 * 4", &mut memories, &mut prev_result).is_ok());
+        assert_eq!(prev_result, 14.0);
+    }
+}